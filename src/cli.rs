@@ -0,0 +1,205 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::{native_token::lamports_to_sol, pubkey::Pubkey, signature::Signature, signature::Signer};
+
+use crate::load_identity;
+
+/// TODO(token-2022): this only covers the legacy SPL Token program; wallets
+/// holding Token-2022 mints won't show those balances here.
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const CONFIRM_TIMEOUT_ATTEMPTS: u32 = 30;
+
+/// The subcommand the binary was invoked with, modeled on the historical
+/// Solana CLI's `wallet.rs` `WalletCommand` enum.
+pub(crate) enum WalletCommand {
+    /// Runs the webhook + control server for the given wallet address (the
+    /// original behavior). The `bool` is whether `--dry-run` was passed.
+    Run(String, bool),
+    Balance,
+    Confirm(String),
+    Address,
+    TxCount,
+    Airdrop(f64),
+}
+
+const USAGE: &str =
+    "Usage: limit-buster <run|monitor> <wallet-address> [--dry-run] | balance | confirm <signature> | address | tx-count | airdrop <amount-sol>";
+
+/// Parses `args` (as returned by `env::args().collect()`) into a
+/// `WalletCommand`. `--dry-run` is a global flag accepted anywhere in the
+/// argument list; it only has an effect on the `run`/`monitor` subcommand.
+pub(crate) fn parse_args(args: &[String]) -> Result<WalletCommand, String> {
+    let mut args: Vec<String> = args.to_vec();
+    let dry_run = match args.iter().position(|a| a == "--dry-run") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("run") | Some("monitor") => {
+            let wallet = args.get(2).cloned().ok_or_else(|| format!("Missing <wallet-address>.\n{}", USAGE))?;
+            Ok(WalletCommand::Run(wallet, dry_run))
+        }
+        Some("balance") => Ok(WalletCommand::Balance),
+        Some("confirm") => {
+            let signature = args.get(2).cloned().ok_or_else(|| format!("Missing <signature>.\n{}", USAGE))?;
+            Ok(WalletCommand::Confirm(signature))
+        }
+        Some("address") => Ok(WalletCommand::Address),
+        Some("tx-count") => Ok(WalletCommand::TxCount),
+        Some("airdrop") => {
+            let amount_sol = args
+                .get(2)
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or_else(|| format!("Missing or invalid <amount-sol>.\n{}", USAGE))?;
+            Ok(WalletCommand::Airdrop(amount_sol))
+        }
+        Some(other) => Err(format!("Unknown command: {}.\n{}", other, USAGE)),
+        None => Err(format!("Missing command.\n{}", USAGE)),
+    }
+}
+
+/// Prints the configured keypair's SOL and SPL token balances.
+pub(crate) async fn balance() -> Result<(), Box<dyn std::error::Error>> {
+    let (keypair, rpc_client, _helius_api_key) = load_identity()?;
+    let pubkey = keypair.pubkey();
+
+    let sol_balance = rpc_client.get_balance(&pubkey)?;
+    println!("SOL: {}", lamports_to_sol(sol_balance));
+
+    let token_accounts = rpc_client.get_token_accounts_by_owner(
+        &pubkey,
+        TokenAccountsFilter::ProgramId(Pubkey::from_str(TOKEN_PROGRAM_ID)?),
+    )?;
+    for account in token_accounts {
+        let UiAccountData::Json(parsed) = account.account.data else {
+            continue;
+        };
+        let info = &parsed.parsed["info"];
+        let mint = info["mint"].as_str().unwrap_or("unknown");
+        let ui_amount = info["tokenAmount"]["uiAmountString"].as_str().unwrap_or("0");
+        println!("{}: {}", mint, ui_amount);
+    }
+
+    Ok(())
+}
+
+/// Polls `getSignatureStatuses` until `signature` is confirmed or finalized,
+/// or the poll budget is exhausted.
+pub(crate) async fn confirm(signature: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (_keypair, rpc_client, _helius_api_key) = load_identity()?;
+    let signature = Signature::from_str(signature)?;
+
+    for attempt in 1..=CONFIRM_TIMEOUT_ATTEMPTS {
+        let statuses = rpc_client.get_signature_statuses(&[signature])?.value;
+        if let Some(Some(status)) = statuses.into_iter().next() {
+            if let Some(err) = status.err {
+                return Err(format!("Transaction failed: {:?}", err).into());
+            }
+            if status.satisfies_commitment(rpc_client.commitment()) {
+                println!("Confirmed: {}", signature);
+                return Ok(());
+            }
+        }
+        println!("Waiting for confirmation... (attempt {}/{})", attempt, CONFIRM_TIMEOUT_ATTEMPTS);
+        tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+    }
+
+    Err(format!("Timed out waiting for confirmation of {}", signature).into())
+}
+
+/// Prints the configured keypair's public key.
+pub(crate) fn address() -> Result<(), Box<dyn std::error::Error>> {
+    let (keypair, _rpc_client, _helius_api_key) = load_identity()?;
+    println!("{}", keypair.pubkey());
+    Ok(())
+}
+
+/// Prints the cluster's total transaction count, matching the historical
+/// Solana `wallet.rs` `tx-count` command (a cluster-wide count, not a
+/// wallet-specific one).
+pub(crate) async fn tx_count() -> Result<(), Box<dyn std::error::Error>> {
+    let (_keypair, rpc_client, _helius_api_key) = load_identity()?;
+    let count = rpc_client.get_transaction_count()?;
+    println!("Transaction count: {}", count);
+    Ok(())
+}
+
+/// Requests a devnet/testnet airdrop of `amount_sol` SOL to the configured
+/// keypair and waits for it to confirm.
+pub(crate) async fn airdrop(amount_sol: f64) -> Result<(), Box<dyn std::error::Error>> {
+    let (keypair, rpc_client, _helius_api_key) = load_identity()?;
+    let lamports = (amount_sol * solana_sdk::native_token::LAMPORTS_PER_SOL as f64) as u64;
+
+    let signature = rpc_client.request_airdrop(&keypair.pubkey(), lamports)?;
+    println!("Airdrop requested: {}", signature);
+    confirm(&signature.to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(argv: &[&str]) -> Vec<String> {
+        argv.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn run_with_dry_run_before_the_wallet_arg() {
+        let parsed = parse_args(&args(&["limit-buster", "run", "--dry-run", "some-wallet"]));
+        match parsed {
+            Ok(WalletCommand::Run(wallet, dry_run)) => {
+                assert_eq!(wallet, "some-wallet");
+                assert!(dry_run);
+            }
+            other => panic!("expected WalletCommand::Run, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn run_with_dry_run_after_the_wallet_arg() {
+        let parsed = parse_args(&args(&["limit-buster", "run", "some-wallet", "--dry-run"]));
+        match parsed {
+            Ok(WalletCommand::Run(wallet, dry_run)) => {
+                assert_eq!(wallet, "some-wallet");
+                assert!(dry_run);
+            }
+            other => panic!("expected WalletCommand::Run, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn run_missing_wallet_arg_is_an_error() {
+        let parsed = parse_args(&args(&["limit-buster", "run"]));
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn airdrop_with_a_non_numeric_amount_is_an_error() {
+        let parsed = parse_args(&args(&["limit-buster", "airdrop", "not-a-number"]));
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn airdrop_with_a_valid_amount_parses() {
+        let parsed = parse_args(&args(&["limit-buster", "airdrop", "1.5"]));
+        match parsed {
+            Ok(WalletCommand::Airdrop(amount_sol)) => assert_eq!(amount_sol, 1.5),
+            other => panic!("expected WalletCommand::Airdrop, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn unknown_command_is_an_error() {
+        let parsed = parse_args(&args(&["limit-buster", "moon"]));
+        assert!(parsed.is_err());
+    }
+}
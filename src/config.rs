@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+use crate::priority_fee::PriorityFeeConfig;
+
+/// Trade parameters that can be changed at runtime via the control
+/// server's `set_config` method, instead of requiring a restart.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct RuntimeConfig {
+    pub slippage: f64,
+    /// Fallback trade size when the followed wallet's own spend can't be
+    /// decoded (e.g. a USDC-denominated swap with no SOL price oracle).
+    pub trade_size_lamports: u64,
+    /// Fraction of the followed wallet's decoded spend to mirror.
+    pub sizing_fraction: f64,
+    /// Upper bound on a single mirrored swap, regardless of sizing_fraction.
+    pub max_trade_size_lamports: u64,
+    /// Upper bound on cumulative lamports mirrored per followed wallet.
+    pub per_wallet_cap_lamports: u64,
+    pub priority_fee: PriorityFeeConfig,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            slippage: 0.5,
+            trade_size_lamports: 1_000_000,
+            sizing_fraction: 0.10,
+            max_trade_size_lamports: 50_000_000,
+            per_wallet_cap_lamports: 500_000_000,
+            priority_fee: PriorityFeeConfig::default(),
+        }
+    }
+}
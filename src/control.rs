@@ -0,0 +1,230 @@
+use axum::{extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::AppState;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Deserialize, Debug)]
+struct JsonRpcRequest {
+    #[allow(dead_code)]
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    id: Value,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonRpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<JsonRpcError>,
+    id: Value,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+impl JsonRpcResponse {
+    fn ok(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0", result: Some(result), error: None, id }
+    }
+
+    fn err(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            result: None,
+            error: Some(JsonRpcError { code, message: message.into() }),
+            id,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct WalletParams {
+    wallet: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct SetConfigParams {
+    slippage: Option<f64>,
+    trade_size_lamports: Option<u64>,
+    sizing_fraction: Option<f64>,
+    max_trade_size_lamports: Option<u64>,
+    per_wallet_cap_lamports: Option<u64>,
+    priority_fee_cap_microlamports: Option<u64>,
+}
+
+/// The JSON-RPC 2.0 control server, mounted alongside the webhook router so
+/// operators can reconfigure the bot without a restart.
+pub(crate) fn router() -> Router<AppState> {
+    Router::new().route("/rpc", post(control_handler))
+}
+
+async fn control_handler(State(state): State<AppState>, Json(request): Json<JsonRpcRequest>) -> Json<JsonRpcResponse> {
+    let id = request.id.clone();
+    let response = match request.method.as_str() {
+        "add_wallet" => add_wallet(&state, request.params).await,
+        "remove_wallet" => remove_wallet(&state, request.params).await,
+        "list_wallets" => list_wallets(&state).await,
+        "get_positions" => get_positions(&state).await,
+        "set_config" => set_config(&state, request.params).await,
+        other => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+    };
+
+    Json(match response {
+        Ok(result) => JsonRpcResponse::ok(id, result),
+        Err((code, message)) => JsonRpcResponse::err(id, code, message),
+    })
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+async fn add_wallet(state: &AppState, params: Value) -> Result<Value, (i64, String)> {
+    let params: WalletParams = parse_params(params)?;
+    let inserted = state.wallets.write().await.insert(params.wallet);
+    Ok(json!({ "added": inserted }))
+}
+
+async fn remove_wallet(state: &AppState, params: Value) -> Result<Value, (i64, String)> {
+    let params: WalletParams = parse_params(params)?;
+    let removed = state.wallets.write().await.remove(&params.wallet);
+    Ok(json!({ "removed": removed }))
+}
+
+async fn list_wallets(state: &AppState) -> Result<Value, (i64, String)> {
+    let wallets: Vec<String> = state.wallets.read().await.iter().cloned().collect();
+    Ok(json!({ "wallets": wallets }))
+}
+
+async fn get_positions(state: &AppState) -> Result<Value, (i64, String)> {
+    let positions = state.positions.read().await.clone();
+    Ok(json!({ "positions": positions }))
+}
+
+async fn set_config(state: &AppState, params: Value) -> Result<Value, (i64, String)> {
+    let params: SetConfigParams = parse_params(params)?;
+    let mut config = state.runtime_config.write().await;
+    if let Some(slippage) = params.slippage {
+        config.slippage = slippage;
+    }
+    if let Some(trade_size_lamports) = params.trade_size_lamports {
+        config.trade_size_lamports = trade_size_lamports;
+    }
+    if let Some(sizing_fraction) = params.sizing_fraction {
+        config.sizing_fraction = sizing_fraction;
+    }
+    if let Some(max_trade_size_lamports) = params.max_trade_size_lamports {
+        config.max_trade_size_lamports = max_trade_size_lamports;
+    }
+    if let Some(per_wallet_cap_lamports) = params.per_wallet_cap_lamports {
+        config.per_wallet_cap_lamports = per_wallet_cap_lamports;
+    }
+    if let Some(cap) = params.priority_fee_cap_microlamports {
+        config.priority_fee.cap_microlamports = cap;
+    }
+    Ok(json!({ "config": config.clone() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RuntimeConfig;
+    use crate::middleware::build_default_pipeline;
+    use solana_client::rpc_client::RpcClient;
+    use solana_sdk::signature::Keypair;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn test_state() -> AppState {
+        AppState {
+            wallets: Arc::new(RwLock::new(HashSet::from(["tracked-wallet".to_string()]))),
+            rpc_client: Arc::new(RpcClient::new("http://localhost:8899".to_string())),
+            keypair: Arc::new(Keypair::new()),
+            http_client: Arc::new(reqwest::Client::new()),
+            helius_api_key: "test-key".to_string(),
+            runtime_config: Arc::new(RwLock::new(RuntimeConfig::default())),
+            positions: Arc::new(RwLock::new(Vec::new())),
+            wallet_spend_totals: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            dry_run: false,
+            swap_pipeline: Arc::new(build_default_pipeline()),
+        }
+    }
+
+    async fn dispatch(state: &AppState, method: &str, params: Value) -> JsonRpcResponse {
+        let Json(response) = control_handler(
+            State(state.clone()),
+            Json(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                method: method.to_string(),
+                params,
+                id: json!(1),
+            }),
+        )
+        .await;
+        response
+    }
+
+    #[tokio::test]
+    async fn add_wallet_inserts_and_reports_new() {
+        let state = test_state();
+        let response = dispatch(&state, "add_wallet", json!({ "wallet": "new-wallet" })).await;
+        assert_eq!(response.result, Some(json!({ "added": true })));
+        assert!(state.wallets.read().await.contains("new-wallet"));
+    }
+
+    #[tokio::test]
+    async fn remove_wallet_reports_whether_it_was_tracked() {
+        let state = test_state();
+        let response = dispatch(&state, "remove_wallet", json!({ "wallet": "tracked-wallet" })).await;
+        assert_eq!(response.result, Some(json!({ "removed": true })));
+        assert!(!state.wallets.read().await.contains("tracked-wallet"));
+
+        let response = dispatch(&state, "remove_wallet", json!({ "wallet": "tracked-wallet" })).await;
+        assert_eq!(response.result, Some(json!({ "removed": false })));
+    }
+
+    #[tokio::test]
+    async fn list_wallets_returns_tracked_set() {
+        let state = test_state();
+        let response = dispatch(&state, "list_wallets", json!({})).await;
+        assert_eq!(response.result, Some(json!({ "wallets": ["tracked-wallet"] })));
+    }
+
+    #[tokio::test]
+    async fn get_positions_returns_current_positions() {
+        let state = test_state();
+        let response = dispatch(&state, "get_positions", json!({})).await;
+        assert_eq!(response.result, Some(json!({ "positions": [] })));
+    }
+
+    #[tokio::test]
+    async fn set_config_updates_only_provided_fields() {
+        let state = test_state();
+        let response = dispatch(&state, "set_config", json!({ "slippage": 1.5 })).await;
+        assert!(response.error.is_none());
+        let config = state.runtime_config.read().await;
+        assert_eq!(config.slippage, 1.5);
+        assert_eq!(config.trade_size_lamports, RuntimeConfig::default().trade_size_lamports);
+    }
+
+    #[tokio::test]
+    async fn unknown_method_returns_method_not_found() {
+        let state = test_state();
+        let response = dispatch(&state, "nonexistent", json!({})).await;
+        let error = response.error.expect("expected an error response");
+        assert_eq!(error.code, METHOD_NOT_FOUND);
+    }
+}
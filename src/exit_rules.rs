@@ -0,0 +1,194 @@
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, Duration};
+
+use crate::middleware::SwapIntent;
+use crate::{AppState, SOL_MINT};
+
+const POSITIONS_FILE: &str = "positions.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Monotonic source of `Position::id`s, so two positions on the same mint
+/// (e.g. the followed wallet bought into it twice) never collide and an
+/// exit monitor can remove exactly the position it opened.
+static NEXT_POSITION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Allocates a fresh, process-unique position id.
+pub(crate) fn next_position_id() -> u64 {
+    NEXT_POSITION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Advances the id counter past every id already in `positions`, so ids
+/// assigned after loading persisted positions can't collide with them.
+pub(crate) fn seed_position_id_counter(positions: &[Position]) {
+    if let Some(max_id) = positions.iter().map(|p| p.id).max() {
+        NEXT_POSITION_ID.fetch_max(max_id + 1, Ordering::Relaxed);
+    }
+}
+
+/// A condition that triggers an automatic exit of a position.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum ExitCondition {
+    /// Exit once the position's SOL value rises above `entry_sol_amount * multiplier`.
+    PriceAbove(f64),
+    /// Exit once the position's SOL value falls below `entry_sol_amount * multiplier`.
+    PriceBelow(f64),
+    /// Exit once the given unix timestamp has passed.
+    AfterTimestamp(u64),
+}
+
+/// A copytrade position opened by mirroring a followed wallet's buy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Position {
+    /// Unique per position, so two open positions on the same mint can be
+    /// told apart when one of them exits. Defaulted on load for positions
+    /// persisted before this field existed.
+    #[serde(default = "next_position_id")]
+    pub id: u64,
+    pub mint: String,
+    pub entry_out_amount: u64,
+    pub entry_sol_amount: u64,
+    pub opened_at: u64,
+    pub exit_conditions: Vec<ExitCondition>,
+}
+
+/// The exit conditions attached to a copytrade position when none are
+/// specified explicitly: take profit at +50%, stop loss at -30%.
+pub(crate) fn default_exit_conditions() -> Vec<ExitCondition> {
+    vec![ExitCondition::PriceAbove(1.5), ExitCondition::PriceBelow(0.7)]
+}
+
+/// Loads persisted positions from disk, so open positions survive a
+/// restart. Returns an empty list if the file doesn't exist or is invalid.
+pub(crate) fn load_positions() -> Vec<Position> {
+    let positions: Vec<Position> = fs::read_to_string(POSITIONS_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    seed_position_id_counter(&positions);
+    positions
+}
+
+/// Overwrites the positions file with the current in-memory positions.
+pub(crate) async fn persist_positions(state: &AppState) {
+    let positions = state.positions.read().await.clone();
+    let serialized = match serde_json::to_string_pretty(&positions) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to serialize positions: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = fs::write(POSITIONS_FILE, serialized) {
+        eprintln!("Failed to persist positions to {}: {}", POSITIONS_FILE, e);
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Spawns a background task that polls `position`'s current SOL value and
+/// fires a reverse swap once any attached `ExitCondition` is met.
+pub(crate) fn spawn_exit_monitor(state: Arc<AppState>, position: Position) {
+    tokio::spawn(async move {
+        let mut ticker = interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match condition_met(&state, &position).await {
+                Ok(true) => {
+                    if let Err(e) = execute_exit(&state, &position).await {
+                        eprintln!("Failed to execute exit for {}: {}", position.mint, e);
+                        continue;
+                    }
+                    remove_position(&state, position.id).await;
+                    return;
+                }
+                Ok(false) => continue,
+                Err(e) => eprintln!("Failed to evaluate exit conditions for {}: {}", position.mint, e),
+            }
+        }
+    });
+}
+
+async fn condition_met(state: &AppState, position: &Position) -> Result<bool, Box<dyn std::error::Error>> {
+    let now = unix_now();
+    let mut needs_price = false;
+    for condition in &position.exit_conditions {
+        match condition {
+            ExitCondition::AfterTimestamp(deadline) if now >= *deadline => return Ok(true),
+            ExitCondition::AfterTimestamp(_) => {}
+            _ => needs_price = true,
+        }
+    }
+    if !needs_price {
+        return Ok(false);
+    }
+
+    let current_sol_amount =
+        quote_out_amount(&state.http_client, &position.mint, SOL_MINT, position.entry_out_amount).await?;
+    for condition in &position.exit_conditions {
+        let met = match condition {
+            ExitCondition::PriceAbove(multiplier) => {
+                current_sol_amount as f64 >= position.entry_sol_amount as f64 * multiplier
+            }
+            ExitCondition::PriceBelow(multiplier) => {
+                current_sol_amount as f64 <= position.entry_sol_amount as f64 * multiplier
+            }
+            ExitCondition::AfterTimestamp(_) => false,
+        };
+        if met {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+async fn execute_exit(state: &Arc<AppState>, position: &Position) -> Result<(), Box<dyn std::error::Error>> {
+    let intent = SwapIntent {
+        input_mint: position.mint.clone(),
+        output_mint: SOL_MINT.to_string(),
+        amount: position.entry_out_amount,
+    };
+    let outcome = state.swap_pipeline.execute(state, intent).await?;
+    println!("Exit executed for {}: signature {}", position.mint, outcome.signature);
+    Ok(())
+}
+
+async fn remove_position(state: &AppState, id: u64) {
+    state.positions.write().await.retain(|p| p.id != id);
+    persist_positions(state).await;
+}
+
+#[derive(Deserialize)]
+struct QuoteResponse {
+    data: Vec<QuoteRoute>,
+}
+
+#[derive(Deserialize)]
+struct QuoteRoute {
+    out_amount: String,
+}
+
+async fn quote_out_amount(
+    http_client: &Client,
+    input_mint: &str,
+    output_mint: &str,
+    amount: u64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let url = format!(
+        "https://quote-api.jup.ag/v4/quote?inputMint={}&outputMint={}&amount={}&slippage=0.5",
+        input_mint, output_mint, amount
+    );
+    let response: QuoteResponse = http_client.get(&url).send().await?.json().await?;
+    let route = response.data.into_iter().next().ok_or("No quote route found")?;
+    Ok(route.out_amount.parse()?)
+}
@@ -3,8 +3,10 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
-use solana_client::rpc_client::RpcClient;
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
 use solana_sdk::{
+    instruction::{AccountMeta, Instruction as CuInstruction},
+    message::Message as CuMessage,
     signature::{Keypair, Signer},
     transaction::Transaction,
     commitment_config::CommitmentConfig,
@@ -14,6 +16,29 @@ use std::sync::Arc;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 
+mod cli;
+mod config;
+mod control;
+mod exit_rules;
+mod middleware;
+mod priority_fee;
+mod sizing;
+use config::RuntimeConfig;
+use exit_rules::Position;
+use middleware::{build_default_pipeline, SwapIntent, SwapMiddleware};
+use priority_fee::PriorityFeeConfig;
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+/// Compute unit limit assumed when `simulateTransaction` can't tell us
+/// `unitsConsumed` (e.g. the simulation call itself fails).
+const DEFAULT_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+
+/// Wrapped SOL's mint address, used as the input side of copytrade buys
+/// and the output side of exit sells.
+pub(crate) const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
 struct WebhookPayload {
@@ -166,12 +191,23 @@ struct JupiterSwapRequest {
 }
 
 #[derive(Clone)]
-struct AppState {
-    wallet: String,
-    rpc_client: Arc<RpcClient>,
-    keypair: Arc<Keypair>,
-    http_client: Arc<Client>,
+pub(crate) struct AppState {
+    pub(crate) wallets: Arc<RwLock<HashSet<String>>>,
+    pub(crate) rpc_client: Arc<RpcClient>,
+    pub(crate) keypair: Arc<Keypair>,
+    pub(crate) http_client: Arc<Client>,
     helius_api_key: String,
+    pub(crate) runtime_config: Arc<RwLock<RuntimeConfig>>,
+    pub(crate) positions: Arc<RwLock<Vec<Position>>>,
+    pub(crate) wallet_spend_totals: Arc<RwLock<HashMap<String, u64>>>,
+    /// When set, swaps are simulated via `simulateTransaction` and logged
+    /// instead of broadcast, so the sizing and exit-rules logic can be
+    /// exercised against live webhook traffic without spending funds.
+    pub(crate) dry_run: bool,
+    /// Built once at startup and shared across every webhook/exit-rule
+    /// swap, so stateful layers like `RateLimit` actually accumulate state
+    /// across calls instead of starting fresh each time.
+    pub(crate) swap_pipeline: Arc<dyn SwapMiddleware>,
 }
 
 async fn webhook_handler(State(state): State<AppState>, body: Bytes) -> impl IntoResponse {
@@ -186,9 +222,17 @@ async fn webhook_handler(State(state): State<AppState>, body: Bytes) -> impl Int
                     let signature = tx.transaction.signatures.get(0).map(|s| s.as_str()).unwrap_or("unknown");
                     println!("Buy detected for tx: {}", signature);
 
+                    let monitored_wallets = state
+                        .wallets
+                        .read()
+                        .await
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ");
                     let log_message = format!(
-                        "Buy detected: Wallet {} made a purchase - Tx: {}\n",
-                        state.wallet, signature
+                        "Buy detected: Monitored wallet(s) [{}] - Tx: {}\n",
+                        monitored_wallets, signature
                     );
                     let mut file = OpenOptions::new()
                         .create(true)
@@ -203,8 +247,30 @@ async fn webhook_handler(State(state): State<AppState>, body: Bytes) -> impl Int
                     }
 
                     if let Some(token_mint) = extract_token_mint(tx) {
-                        match perform_copytrade_swap(&state, &token_mint).await {
-                            Ok(tx_signature) => println!("Copytrade swap executed: {}", tx_signature),
+                        let shared_state = Arc::new(state.clone());
+                        let source_wallet = tx.transaction.message.account_keys.first().cloned().unwrap_or_default();
+                        let decoded_amount = sizing::decode_trade_amount(tx);
+                        let trade_size_lamports = sizing::size_trade_lamports(&state, &source_wallet, decoded_amount).await;
+                        let intent = SwapIntent {
+                            input_mint: SOL_MINT.to_string(),
+                            output_mint: token_mint.clone(),
+                            amount: trade_size_lamports,
+                        };
+                        match state.swap_pipeline.execute(&state, intent).await {
+                            Ok(outcome) => {
+                                println!("Copytrade swap executed: {}", outcome.signature);
+                                let position = Position {
+                                    id: exit_rules::next_position_id(),
+                                    mint: token_mint,
+                                    entry_out_amount: outcome.out_amount,
+                                    entry_sol_amount: trade_size_lamports,
+                                    opened_at: unix_now(),
+                                    exit_conditions: exit_rules::default_exit_conditions(),
+                                };
+                                state.positions.write().await.push(position.clone());
+                                exit_rules::persist_positions(&state).await;
+                                exit_rules::spawn_exit_monitor(shared_state, position);
+                            }
                             Err(e) => eprintln!("Failed to execute copytrade swap: {}", e),
                         }
                     } else {
@@ -220,6 +286,13 @@ async fn webhook_handler(State(state): State<AppState>, body: Bytes) -> impl Int
     StatusCode::OK
 }
 
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 fn is_buy_transaction(tx: &WebhookPayload) -> bool {
     let account_keys = &tx.transaction.message.account_keys;
     let default_key = String::new();
@@ -243,10 +316,17 @@ fn extract_token_mint(tx: &WebhookPayload) -> Option<String> {
     None
 }
 
-async fn perform_copytrade_swap(state: &AppState, token_mint: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Builds, prices and signs the copytrade swap transaction, but does not
+/// broadcast it. Shared by the middleware layers that need the raw
+/// transaction (e.g. to simulate it) and the base layer that sends it.
+pub(crate) async fn build_and_sign_swap_tx(
+    state: &AppState,
+    intent: &SwapIntent,
+) -> Result<(Transaction, u64, Vec<String>), Box<dyn std::error::Error>> {
+    let runtime_config = state.runtime_config.read().await.clone();
     let quote_url = format!(
-        "https://quote-api.jup.ag/v4/quote?inputMint=So11111111111111111111111111111111111111112&outputMint={}&amount=1000000&slippage=0.5",
-        token_mint
+        "https://quote-api.jup.ag/v4/quote?inputMint={}&outputMint={}&amount={}&slippage={}",
+        intent.input_mint, intent.output_mint, intent.amount, runtime_config.slippage
     );
 
     let quote_response: JupiterQuoteResponse = state.http_client
@@ -256,6 +336,8 @@ async fn perform_copytrade_swap(state: &AppState, token_mint: &str) -> Result<St
         .json()
         .await?;
     let route = quote_response.data.into_iter().next().ok_or("No swap route found")?;
+    let out_amount: u64 = route.out_amount.parse()?;
+    let route_labels: Vec<String> = route.market_infos.iter().map(|m| m.label.clone()).collect();
 
     let swap_request = JupiterSwapRequest {
         route,
@@ -275,15 +357,92 @@ async fn perform_copytrade_swap(state: &AppState, token_mint: &str) -> Result<St
         .ok_or("No swap transaction returned")?;
 
     let decoded_tx = BASE64_STANDARD.decode(serialized_tx)?;
-    let mut tx: Transaction = bincode::deserialize(&decoded_tx)?;
+    let unsigned_tx: Transaction = bincode::deserialize(&decoded_tx)?;
+
+    let units_consumed = simulate_units_consumed(&state.rpc_client, &unsigned_tx)
+        .unwrap_or(DEFAULT_COMPUTE_UNIT_LIMIT);
+    let unit_limit = priority_fee::padded_compute_unit_limit(units_consumed);
+    let unit_price = priority_fee::estimate_priority_fee(
+        &state.rpc_client,
+        &unsigned_tx.message.account_keys,
+        &runtime_config.priority_fee,
+    )
+    .unwrap_or(runtime_config.priority_fee.floor_microlamports);
+
+    let mut instructions = priority_fee::compute_budget_instructions(unit_price, unit_limit);
+    instructions.extend(decompile_instructions(&unsigned_tx.message));
+
+    let payer = state.keypair.pubkey();
+    let message = CuMessage::new_with_blockhash(&instructions, Some(&payer), &unsigned_tx.message.recent_blockhash);
+    let mut tx = Transaction::new_unsigned(message);
     tx.sign(&[state.keypair.as_ref()], tx.message.recent_blockhash);
 
-    let signature = state.rpc_client.send_and_confirm_transaction_with_spinner_and_commitment(
-        &tx,
-        CommitmentConfig::confirmed(),
-    )?;
+    println!(
+        "Copytrade swap priority fee: {} microlamports/CU (unit limit {})",
+        unit_price, unit_limit
+    );
+
+    Ok((tx, out_amount, route_labels))
+}
+
+/// Simulates `tx` to read back `unitsConsumed`, skipping signature
+/// verification since the transaction isn't signed yet.
+fn simulate_units_consumed(rpc_client: &RpcClient, tx: &Transaction) -> Option<u64> {
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        ..Default::default()
+    };
+    rpc_client
+        .simulate_transaction_with_config(tx, config)
+        .ok()?
+        .value
+        .units_consumed
+}
+
+/// Reconstructs the `CuInstruction`s a compiled `CuMessage` was built from,
+/// so they can be merged with new instructions (e.g. compute budget) and
+/// recompiled into a fresh message.
+fn decompile_instructions(message: &CuMessage) -> Vec<CuInstruction> {
+    let header = &message.header;
+    let num_required_signatures = header.num_required_signatures as usize;
+    let num_readonly_signed = header.num_readonly_signed_accounts as usize;
+    let num_readonly_unsigned = header.num_readonly_unsigned_accounts as usize;
+    let num_accounts = message.account_keys.len();
+
+    let is_signer = |index: usize| index < num_required_signatures;
+    let is_writable = |index: usize| {
+        if index < num_required_signatures {
+            index < num_required_signatures - num_readonly_signed
+        } else {
+            index < num_accounts - num_readonly_unsigned
+        }
+    };
 
-    Ok(signature.to_string())
+    message
+        .instructions
+        .iter()
+        .map(|ix| {
+            let program_id = message.account_keys[ix.program_id_index as usize];
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = index as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: is_signer(index),
+                        is_writable: is_writable(index),
+                    }
+                })
+                .collect();
+            CuInstruction {
+                program_id,
+                accounts,
+                data: ix.data.clone(),
+            }
+        })
+        .collect()
 }
 
 /*
@@ -308,15 +467,30 @@ async fn health_check_task(http_client: Arc<Client>, helius_api_key: String) {
 }
 */
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Please provide a wallet address");
-        std::process::exit(1);
+/// Builds the priority-fee bidding config, letting operators override the
+/// defaults via environment variables without touching code.
+fn priority_fee_config_from_env() -> PriorityFeeConfig {
+    let default = PriorityFeeConfig::default();
+    PriorityFeeConfig {
+        percentile: env::var("PRIORITY_FEE_PERCENTILE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default.percentile),
+        floor_microlamports: env::var("PRIORITY_FEE_FLOOR_MICROLAMPORTS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default.floor_microlamports),
+        cap_microlamports: env::var("PRIORITY_FEE_CAP_MICROLAMPORTS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default.cap_microlamports),
     }
-    let wallet = args[1].clone();
+}
 
+/// Loads the signing keypair and builds the Helius RPC client shared by
+/// every subcommand, from `SECRET_KEY` / `HELIUS_API_KEY` in the
+/// environment (or `.env`).
+pub(crate) fn load_identity() -> Result<(Arc<Keypair>, Arc<RpcClient>, String), Box<dyn std::error::Error>> {
     match dotenv::dotenv() {
         Ok(_) => println!("Loaded .env file"),
         Err(e) => eprintln!("Warning: Could not load .env file: {}", e),
@@ -326,7 +500,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         eprintln!("Error: SECRET_KEY not found in environment: {}", e);
         e
     })?;
-    println!("SECRET_KEY read from env: {}", secret_key_str); // Debug output
     let secret_key: Vec<u8> = serde_json::from_str(&secret_key_str).map_err(|e| {
         eprintln!("Error parsing SECRET_KEY as JSON: {}", e);
         e
@@ -339,21 +512,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let rpc_url = format!("https://mainnet.helius-rpc.com/?api-key={}", helius_api_key);
     let rpc_client = Arc::new(RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()));
+
+    Ok((keypair, rpc_client, helius_api_key))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    match cli::parse_args(&args) {
+        Ok(cli::WalletCommand::Run(wallet, dry_run)) => run_server(wallet, dry_run).await,
+        Ok(cli::WalletCommand::Balance) => cli::balance().await,
+        Ok(cli::WalletCommand::Confirm(signature)) => cli::confirm(&signature).await,
+        Ok(cli::WalletCommand::Address) => cli::address(),
+        Ok(cli::WalletCommand::TxCount) => cli::tx_count().await,
+        Ok(cli::WalletCommand::Airdrop(amount_sol)) => cli::airdrop(amount_sol).await,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the webhook + control server that mirrors a followed wallet's
+/// buys — the `run`/`monitor` subcommand. In `dry_run` mode, copytrade
+/// swaps are simulated and logged rather than broadcast.
+async fn run_server(wallet: String, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (keypair, rpc_client, helius_api_key) = load_identity()?;
     let http_client = Arc::new(Client::new());
 
+    if dry_run {
+        println!("Running in --dry-run mode: swaps will be simulated, not broadcast");
+    }
+
+    let runtime_config = RuntimeConfig {
+        priority_fee: priority_fee_config_from_env(),
+        ..RuntimeConfig::default()
+    };
+
+    let persisted_positions = exit_rules::load_positions();
+
     let state = AppState {
-        wallet,
+        wallets: Arc::new(RwLock::new(HashSet::from([wallet]))),
         rpc_client,
         keypair,
         http_client: http_client.clone(),
         helius_api_key,
+        runtime_config: Arc::new(RwLock::new(runtime_config)),
+        positions: Arc::new(RwLock::new(persisted_positions.clone())),
+        wallet_spend_totals: Arc::new(RwLock::new(HashMap::new())),
+        dry_run,
+        swap_pipeline: Arc::new(build_default_pipeline()),
     };
 
+    for position in persisted_positions {
+        exit_rules::spawn_exit_monitor(Arc::new(state.clone()), position);
+    }
+
     // Commented out spawning the health check task
     // tokio::spawn(health_check_task(http_client, state.helius_api_key.clone()));
 
     let app = Router::new()
         .route("/notify", post(webhook_handler))
+        .merge(control::router())
         .with_state(state);
 
     let addr = "0.0.0.0:3000".parse()?;
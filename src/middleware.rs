@@ -0,0 +1,302 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::transaction::Transaction;
+use tokio::sync::Mutex;
+
+use crate::{build_and_sign_swap_tx, unix_now, AppState};
+
+/// What to swap, handed down through the middleware stack. A copytrade
+/// buy sets `input_mint` to SOL; an exit sell sets `output_mint` to SOL.
+#[derive(Clone, Debug)]
+pub(crate) struct SwapIntent {
+    pub input_mint: String,
+    pub output_mint: String,
+    pub amount: u64,
+}
+
+/// Result of a completed swap: the broadcast signature plus how much of
+/// `output_mint` was received, so callers can size a resulting position.
+#[derive(Clone, Debug)]
+pub(crate) struct SwapOutcome {
+    pub signature: String,
+    pub out_amount: u64,
+}
+
+/// A layer in the swap execution pipeline. Layers wrap an inner layer and
+/// add behavior (retries, rate limiting, ...) around it; the innermost
+/// layer does the actual Jupiter quote/swap/sign/send. `state` is threaded
+/// through per call rather than captured by each layer, so a single
+/// pipeline instance can be built once and shared across calls (required
+/// for `RateLimit`'s window to actually accumulate).
+#[async_trait]
+pub(crate) trait SwapMiddleware: Send + Sync {
+    async fn execute(&self, state: &AppState, intent: SwapIntent) -> Result<SwapOutcome, Box<dyn std::error::Error>>;
+}
+
+/// A layer that acts on an already-built, signed swap transaction — e.g.
+/// simulating it before handing it to the next layer, or finally
+/// broadcasting it. Stacked beneath `JupiterSwapLayer`, which builds the
+/// transaction once per call; `SwapMiddleware` layers that retry (`Retry`,
+/// `BlockhashRefresh`) call back in for a fresh quote/blockhash rather than
+/// resending the same bytes. Composable independently of `SwapMiddleware`:
+/// dropping `Simulate` here (swapping in `Broadcast` directly) disables
+/// pre-broadcast simulation without touching any other layer.
+#[async_trait]
+pub(crate) trait TxExecutor: Send + Sync {
+    async fn execute(
+        &self,
+        state: &AppState,
+        tx: &Transaction,
+        out_amount: u64,
+        route_labels: &[String],
+    ) -> Result<SwapOutcome, Box<dyn std::error::Error>>;
+}
+
+/// Builds the Jupiter quote + swap transaction and signs it, then hands the
+/// exact same signed bytes to `tx_executor` — whatever that layer does with
+/// them (simulate, broadcast, both) operates on the transaction that was
+/// actually built, never a second independently-rebuilt one.
+pub(crate) struct JupiterSwapLayer<T> {
+    pub tx_executor: T,
+}
+
+#[async_trait]
+impl<T: TxExecutor> SwapMiddleware for JupiterSwapLayer<T> {
+    async fn execute(&self, state: &AppState, intent: SwapIntent) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        let (tx, out_amount, route_labels) = build_and_sign_swap_tx(state, &intent).await?;
+        self.tx_executor.execute(state, &tx, out_amount, &route_labels).await
+    }
+}
+
+/// Simulates `tx` — the literal transaction the caller is about to
+/// broadcast — and aborts before `inner` ever runs if the simulation
+/// predicts an error.
+pub(crate) struct Simulate<T> {
+    pub inner: T,
+}
+
+#[async_trait]
+impl<T: TxExecutor> TxExecutor for Simulate<T> {
+    async fn execute(
+        &self,
+        state: &AppState,
+        tx: &Transaction,
+        out_amount: u64,
+        route_labels: &[String],
+    ) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..Default::default()
+        };
+        let simulation = state.rpc_client.simulate_transaction_with_config(tx, config)?;
+        if let Some(err) = simulation.value.err {
+            return Err(format!("Simulated swap would fail: {:?}", err).into());
+        }
+        self.inner.execute(state, tx, out_amount, route_labels).await
+    }
+}
+
+/// The terminal `TxExecutor`: broadcasts `tx`, unless `state.dry_run` is
+/// set, in which case the would-be fill is logged instead.
+pub(crate) struct Broadcast;
+
+#[async_trait]
+impl TxExecutor for Broadcast {
+    async fn execute(
+        &self,
+        state: &AppState,
+        tx: &Transaction,
+        out_amount: u64,
+        route_labels: &[String],
+    ) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        if state.dry_run {
+            return Ok(log_dry_run_fill(state, tx, out_amount, route_labels));
+        }
+
+        let signature = state
+            .rpc_client
+            .send_and_confirm_transaction_with_spinner_and_commitment(tx, CommitmentConfig::confirmed())?;
+        Ok(SwapOutcome { signature: signature.to_string(), out_amount })
+    }
+}
+
+/// Simulates `tx` to read back `unitsConsumed`/logs for the dry-run report,
+/// logs the would-be fill (expected `out_amount`, route labels, predicted
+/// compute units and logs) to the wallet-monitor log instead of
+/// broadcasting, and returns a synthetic `SwapOutcome` so the sizing and
+/// exit-rules logic can still track a "paper" position. A failed
+/// simulation here is only ever logged, not propagated, since dry-run mode
+/// is explicitly for observing what *would* happen.
+fn log_dry_run_fill(state: &AppState, tx: &Transaction, out_amount: u64, route_labels: &[String]) -> SwapOutcome {
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        ..Default::default()
+    };
+    let simulation = match state.rpc_client.simulate_transaction_with_config(tx, config) {
+        Ok(simulation) => Some(simulation),
+        Err(e) => {
+            eprintln!("Dry-run simulation failed: {}", e);
+            None
+        }
+    };
+    let (units_consumed, logs) = simulation
+        .map(|s| (s.value.units_consumed, s.value.logs.unwrap_or_default()))
+        .unwrap_or_default();
+
+    let message = format!(
+        "[DRY RUN] Would-be fill: out_amount={}, route=[{}], units_consumed={:?}\n{}",
+        out_amount,
+        route_labels.join(" -> "),
+        units_consumed,
+        logs.join("\n")
+    );
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("/var/log/wallet-monitor.log")
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to open log file: {}", e);
+            std::fs::File::create("/var/log/wallet-monitor.log").unwrap()
+        });
+    if let Err(e) = writeln!(file, "{}", message) {
+        eprintln!("Failed to write to log file: {}", e);
+    }
+
+    SwapOutcome { signature: format!("DRY-RUN-{}", unix_now()), out_amount }
+}
+
+/// Rebuilds and retries once when the inner layer reports a stale
+/// blockhash — a fresh Jupiter quote carries a fresh blockhash.
+pub(crate) struct BlockhashRefresh<M> {
+    pub inner: M,
+}
+
+#[async_trait]
+impl<M: SwapMiddleware> SwapMiddleware for BlockhashRefresh<M> {
+    async fn execute(&self, state: &AppState, intent: SwapIntent) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        match self.inner.execute(state, intent.clone()).await {
+            Err(e) if e.to_string().contains("BlockhashNotFound") || e.to_string().contains("Blockhash not found") => {
+                println!("Blockhash expired, retrying with a fresh quote");
+                self.inner.execute(state, intent).await
+            }
+            other => other,
+        }
+    }
+}
+
+/// Retries the inner layer on transient RPC errors with exponential
+/// backoff.
+pub(crate) struct Retry<M> {
+    pub inner: M,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl<M> Retry<M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+fn is_transient(error: &(dyn std::error::Error)) -> bool {
+    let message = error.to_string().to_lowercase();
+    message.contains("timed out")
+        || message.contains("timeout")
+        || message.contains("connection")
+        || message.contains("rate limit")
+        || message.contains("too many requests")
+}
+
+#[async_trait]
+impl<M: SwapMiddleware> SwapMiddleware for Retry<M> {
+    async fn execute(&self, state: &AppState, intent: SwapIntent) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.execute(state, intent.clone()).await {
+                Ok(outcome) => return Ok(outcome),
+                Err(e) if attempt < self.max_retries && is_transient(e.as_ref()) => {
+                    let delay = self.base_delay * 2u32.pow(attempt);
+                    println!(
+                        "Transient error ({}), retrying in {:?} (attempt {}/{})",
+                        e,
+                        delay,
+                        attempt + 1,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Caps how many swaps can go out in a sliding time window. Must be part of
+/// a pipeline instance that's built once and reused across calls — a
+/// freshly built `RateLimit` would have an empty `recent` on every call and
+/// could never actually trip its limit.
+pub(crate) struct RateLimit<M> {
+    pub inner: M,
+    pub max_per_window: usize,
+    pub window: Duration,
+    recent: Mutex<Vec<Instant>>,
+}
+
+impl<M> RateLimit<M> {
+    pub fn new(inner: M, max_per_window: usize, window: Duration) -> Self {
+        Self {
+            inner,
+            max_per_window,
+            window,
+            recent: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl<M: SwapMiddleware> SwapMiddleware for RateLimit<M> {
+    async fn execute(&self, state: &AppState, intent: SwapIntent) -> Result<SwapOutcome, Box<dyn std::error::Error>> {
+        {
+            let mut recent = self.recent.lock().await;
+            let now = Instant::now();
+            recent.retain(|t| now.duration_since(*t) < self.window);
+            if recent.len() >= self.max_per_window {
+                return Err(format!(
+                    "Rate limit exceeded: {} swaps already submitted in the last {:?}",
+                    recent.len(),
+                    self.window
+                )
+                .into());
+            }
+            recent.push(now);
+        }
+        self.inner.execute(state, intent).await
+    }
+}
+
+/// Assembles the default swap pipeline: rate limiting, retries and a
+/// blockhash refresh around a base layer that simulates and then
+/// broadcasts the exact same signed transaction. Build this once per
+/// process (e.g. in `AppState`) and share it across calls — the
+/// `RateLimit` window only means anything if the same instance handles
+/// every swap. Callers that want a stack without pre-broadcast simulation
+/// can build their own `JupiterSwapLayer { tx_executor: Broadcast }`
+/// instead of using this default — no edits to this module required.
+pub(crate) fn build_default_pipeline() -> impl SwapMiddleware {
+    let base = JupiterSwapLayer { tx_executor: Simulate { inner: Broadcast } };
+    let blockhash_refreshed = BlockhashRefresh { inner: base };
+    let retried = Retry::new(blockhash_refreshed);
+    RateLimit::new(retried, 5, Duration::from_secs(10))
+}
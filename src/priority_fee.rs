@@ -0,0 +1,111 @@
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey};
+
+/// Percentage headroom added on top of a transaction's simulated
+/// `unitsConsumed` when deriving the compute unit limit.
+const COMPUTE_UNIT_LIMIT_PADDING_PCT: u64 = 20;
+
+/// Tunables for how aggressively we bid on priority fees.
+#[derive(Clone, Debug, Serialize)]
+pub struct PriorityFeeConfig {
+    /// Percentile (0.0-100.0) of the recent per-CU price sample to bid.
+    pub percentile: f64,
+    /// Never bid below this many microlamports per CU, even if the recent
+    /// sample is all zero (e.g. an idle program).
+    pub floor_microlamports: u64,
+    /// Never bid above this many microlamports per CU, so a single outlier
+    /// slot can't drain the wallet on fees.
+    pub cap_microlamports: u64,
+}
+
+impl Default for PriorityFeeConfig {
+    fn default() -> Self {
+        Self {
+            percentile: 75.0,
+            floor_microlamports: 1,
+            cap_microlamports: 1_000_000,
+        }
+    }
+}
+
+/// Queries `getRecentPrioritizationFees` for `accounts` and returns the
+/// `percentile`-th per-CU microlamport price from the recent sample,
+/// clamped to `[floor_microlamports, cap_microlamports]`.
+pub fn estimate_priority_fee(
+    rpc_client: &RpcClient,
+    accounts: &[Pubkey],
+    config: &PriorityFeeConfig,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let recent_fees = rpc_client.get_recent_prioritization_fees(accounts)?;
+
+    let mut sample: Vec<u64> = recent_fees
+        .iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    sample.sort_unstable();
+
+    let bid = percentile(&sample, config.percentile).unwrap_or(config.floor_microlamports);
+
+    Ok(bid.clamp(config.floor_microlamports, config.cap_microlamports))
+}
+
+/// Nearest-rank percentile over an already-sorted sample.
+fn percentile(sorted_sample: &[u64], percentile: f64) -> Option<u64> {
+    if sorted_sample.is_empty() {
+        return None;
+    }
+    let rank = ((percentile / 100.0) * sorted_sample.len() as f64).ceil() as usize;
+    let index = rank.saturating_sub(1).min(sorted_sample.len() - 1);
+    Some(sorted_sample[index])
+}
+
+/// Pads a simulated `unitsConsumed` figure by `COMPUTE_UNIT_LIMIT_PADDING_PCT`
+/// to leave headroom for the compute budget instructions themselves.
+pub fn padded_compute_unit_limit(units_consumed: u64) -> u32 {
+    let padded = units_consumed + (units_consumed * COMPUTE_UNIT_LIMIT_PADDING_PCT / 100);
+    padded.min(u32::MAX as u64) as u32
+}
+
+/// Builds the `set_compute_unit_price` / `set_compute_unit_limit`
+/// instruction pair to prepend before signing.
+pub fn compute_budget_instructions(unit_price_microlamports: u64, unit_limit: u32) -> Vec<Instruction> {
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(unit_price_microlamports),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_on_empty_sample_returns_none() {
+        assert_eq!(percentile(&[], 75.0), None);
+    }
+
+    #[test]
+    fn percentile_on_single_element_sample() {
+        assert_eq!(percentile(&[5], 75.0), Some(5));
+    }
+
+    #[test]
+    fn percentile_on_all_zero_sample() {
+        assert_eq!(percentile(&[0, 0, 0, 0], 50.0), Some(0));
+    }
+
+    #[test]
+    fn percentile_clamps_an_out_of_range_rank_to_the_last_element() {
+        // 200.0 drives the nearest-rank computation past `sorted_sample.len()`,
+        // exercising the `.min(sorted_sample.len() - 1)` guard.
+        assert_eq!(percentile(&[1, 2, 3], 200.0), Some(3));
+    }
+
+    #[test]
+    fn percentile_picks_the_nearest_rank() {
+        let sample = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+        assert_eq!(percentile(&sample, 50.0), Some(50));
+        assert_eq!(percentile(&sample, 90.0), Some(90));
+    }
+}
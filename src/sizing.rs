@@ -0,0 +1,137 @@
+use crate::{AppState, Header, Message, TokenBalance, TransactionData, WebhookPayload};
+
+/// USDC's mint address, the other asset (besides SOL) copytraded swaps are
+/// commonly denominated in.
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+/// Rent-exempt minimum for a new SPL Token account (165 bytes). A wallet's
+/// first buy of a mint often creates its destination ATA, which pays this
+/// out of the fee payer's native SOL balance on top of `meta.fee`. A SOL
+/// delta at or below this is ATA-creation rent, not an actual
+/// SOL-denominated spend, and must not be misread as one.
+const TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS: u64 = 2_039_280;
+
+/// The followed wallet's actual input amount for a detected swap.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum DecodedTradeAmount {
+    Sol(u64),
+    Usdc(u64),
+}
+
+/// Estimates how much the followed wallet spent on a swap by diffing
+/// `Meta`'s pre/post native SOL balances for the transaction's fee payer
+/// (account index 0, the followed wallet), falling back to a USDC token
+/// balance diff. This is a balance-delta heuristic, not real instruction
+/// decoding of the Jupiter/Raydium swap instruction itself — it can't tell
+/// a swap's SOL leg apart from any other SOL movement the fee payer's
+/// account saw in the same transaction (e.g. an unrelated transfer bundled
+/// into the same tx), beyond filtering out ATA-creation rent via
+/// `TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS`.
+pub(crate) fn decode_trade_amount(tx: &WebhookPayload) -> Option<DecodedTradeAmount> {
+    let meta = tx.meta.as_ref()?;
+    let wallet = tx.transaction.message.account_keys.first()?;
+
+    if let (Some(&pre), Some(&post)) = (meta.pre_balances.first(), meta.post_balances.first()) {
+        let fee = meta.fee.unwrap_or(0);
+        let spent = pre.saturating_sub(post).saturating_sub(fee);
+        if spent > TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS {
+            return Some(DecodedTradeAmount::Sol(spent));
+        }
+    }
+
+    let pre_usdc = usdc_balance(&meta.pre_token_balances, wallet);
+    let post_usdc = usdc_balance(&meta.post_token_balances, wallet);
+    let spent = pre_usdc.saturating_sub(post_usdc);
+    if spent > 0 {
+        return Some(DecodedTradeAmount::Usdc(spent));
+    }
+
+    None
+}
+
+fn usdc_balance(balances: &[TokenBalance], owner: &str) -> u64 {
+    balances
+        .iter()
+        .find(|b| b.mint == USDC_MINT && b.owner == owner)
+        .and_then(|b| b.ui_token_amount.amount.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Sizes our own swap as a configurable fraction of the followed wallet's
+/// decoded spend, subject to a per-trade cap and a cumulative per-wallet
+/// cap. Falls back to `trade_size_lamports` when the spend couldn't be
+/// decoded in SOL terms (e.g. a USDC-denominated swap, since we have no
+/// price oracle to convert it).
+pub(crate) async fn size_trade_lamports(
+    state: &AppState,
+    wallet: &str,
+    decoded: Option<DecodedTradeAmount>,
+) -> u64 {
+    let config = state.runtime_config.read().await.clone();
+    let sized = match decoded {
+        Some(DecodedTradeAmount::Sol(lamports)) => (lamports as f64 * config.sizing_fraction) as u64,
+        Some(DecodedTradeAmount::Usdc(_)) => {
+            println!("Decoded a USDC-denominated trade with no SOL price oracle; using the default trade size");
+            config.trade_size_lamports
+        }
+        None => config.trade_size_lamports,
+    };
+
+    let capped_per_trade = sized.min(config.max_trade_size_lamports);
+
+    let mut totals = state.wallet_spend_totals.write().await;
+    let spent_so_far = *totals.get(wallet).unwrap_or(&0);
+    let remaining_wallet_budget = config.per_wallet_cap_lamports.saturating_sub(spent_so_far);
+    let final_amount = capped_per_trade.min(remaining_wallet_budget);
+
+    *totals.entry(wallet.to_string()).or_insert(0) += final_amount;
+    final_amount
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Meta;
+
+    fn webhook_with_sol_delta(pre: u64, post: u64) -> WebhookPayload {
+        WebhookPayload {
+            block_time: None,
+            index_within_block: None,
+            slot: None,
+            meta: Some(Meta {
+                pre_balances: vec![pre],
+                post_balances: vec![post],
+                ..Default::default()
+            }),
+            transaction: TransactionData {
+                signatures: vec![],
+                message: Message {
+                    account_keys: vec!["So1anaWallet111111111111111111111111111111".to_string()],
+                    instructions: vec![],
+                    address_table_lookups: None,
+                    header: Header {
+                        num_readonly_signed_accounts: 0,
+                        num_readonly_unsigned_accounts: 0,
+                        num_required_signatures: 1,
+                    },
+                    recent_blockhash: "11111111111111111111111111111111111111111111".to_string(),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn decode_trade_amount_treats_a_delta_at_the_rent_exemption_boundary_as_rent_only() {
+        let tx = webhook_with_sol_delta(TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS, 0);
+        assert!(decode_trade_amount(&tx).is_none());
+    }
+
+    #[test]
+    fn decode_trade_amount_treats_a_delta_just_above_the_rent_exemption_boundary_as_a_spend() {
+        let tx = webhook_with_sol_delta(TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS + 1, 0);
+        match decode_trade_amount(&tx) {
+            Some(DecodedTradeAmount::Sol(spent)) => assert_eq!(spent, TOKEN_ACCOUNT_RENT_EXEMPTION_LAMPORTS + 1),
+            other => panic!("expected a decoded SOL spend, got {:?}", other),
+        }
+    }
+}